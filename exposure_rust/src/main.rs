@@ -10,10 +10,164 @@ use hhmmss::Hhmmss;
 use std::collections::hash_map::Entry::Vacant;
 use gpx::read;
 use gpx::{Gpx, TrackSegment};
+use rstar::{RTree, RTreeObject, PointDistance, AABB};
+use geojson::{Feature, FeatureCollection, Geometry, JsonObject};
+
+// equirectangular degrees-to-meters scale used by `quick_distance`, reused here
+// so the R-tree search radius matches the distances it later filters against
+const METERS_PER_DEGREE: f64 = 111319.0;
+
+// a degree of longitude covers less ground than a degree of latitude away
+// from the equator; `quick_distance` accounts for this by scaling delta-
+// longitude by cos(lat). The R-tree indexes/queries plain Euclidean degree
+// distance, so both camera positions and query points are projected through
+// this factor first to keep the index's notion of "distance" consistent with
+// the metric the acceptance check actually uses (otherwise the query circle
+// is too narrow in longitude away from the equator and drops true candidates)
+fn lon_scale(lat_deg: f64) -> f64 {
+    lat_deg.to_radians().cos().max(1e-6)
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+enum DistanceModel {
+    #[default]
+    Quick,
+    Haversine,
+}
+
+/// Tunable parameters that used to be compile-time constants. Loaded from an
+/// optional TOML file (third CLI arg); any field left out of the file keeps
+/// its built-in default.
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(default)]
+struct Config {
+    earth_radius_km: f64,
+    resolution_m: f64,
+    accept_range_m: f64,
+    default_heading_deg: f64,
+    distance_model: DistanceModel,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            earth_radius_km: 6371.0,
+            resolution_m: 0.5,
+            accept_range_m: 1.0,
+            default_heading_deg: 180.0,
+            distance_model: DistanceModel::Quick,
+        }
+    }
+}
+
+fn load_config(path: &str) -> Result<Config, Box<dyn Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(toml::from_str(&contents)?)
+}
+
+// degrees packed into an i32 at this resolution (~1.1cm at the equator);
+// plenty of precision for camera positions while halving storage vs. f64
+const GEOCOORD_SCALE: f64 = 1e7;
+const GEOCOORD_INVALID: i32 = i32::MIN;
+
+/// A compact fixed-point degree coordinate, used to store camera positions.
+/// `i32::MIN` is reserved as the "invalid/unset" sentinel so a non-finite or
+/// out-of-range CSV value surfaces as `is_valid() == false` instead of
+/// silently becoming 0.0 (a real coordinate, unlike an out-of-range sentinel).
+/// A value that is not a number at all fails deserialization instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct GeoCoord(i32);
+
+impl GeoCoord {
+    fn from_degrees(value: f64) -> Self {
+        let scaled = (value * GEOCOORD_SCALE).round();
+        if !scaled.is_finite() || scaled <= GEOCOORD_INVALID as f64 || scaled > i32::MAX as f64 {
+            return GeoCoord(GEOCOORD_INVALID);
+        }
+        GeoCoord(scaled as i32)
+    }
+
+    fn to_degrees(self) -> f64 {
+        self.0 as f64 / GEOCOORD_SCALE
+    }
+
+    #[allow(dead_code)]
+    fn from_raw(raw: i32) -> Self {
+        GeoCoord(raw)
+    }
+
+    #[allow(dead_code)]
+    fn to_raw(self) -> i32 {
+        self.0
+    }
+
+    fn is_valid(self) -> bool {
+        self.0 != GEOCOORD_INVALID
+    }
+}
+
+impl Default for GeoCoord {
+    fn default() -> Self {
+        GeoCoord(GEOCOORD_INVALID)
+    }
+}
 
-const R: f64 = 6371.0;
-const RESOLUTION: f64 = 0.5;
-const ACCEPTRANGE: f64 = 1.0;
+impl<'de> Deserialize<'de> for GeoCoord {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = f64::deserialize(deserializer)?;
+        Ok(GeoCoord::from_degrees(value))
+    }
+}
+
+/// A camera position indexed by `rstar`, carrying the camera's index into the
+/// original `Vec<Camera>` so matches can be mapped back without cloning.
+/// `proj_lon` is the camera's longitude pre-scaled by [`lon_scale`] so the
+/// index's Euclidean degree-distance lines up with `quick_distance`/
+/// `haversine_distance`; query points must be projected the same way.
+#[derive(Debug, Clone, Copy)]
+struct CameraPoint {
+    index: usize,
+    lat: f64,
+    proj_lon: f64,
+}
+
+impl RTreeObject for CameraPoint {
+    type Envelope = AABB<[f64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        AABB::from_point([self.proj_lon, self.lat])
+    }
+}
+
+impl PointDistance for CameraPoint {
+    fn distance_2(&self, point: &[f64; 2]) -> f64 {
+        let dlon = self.proj_lon - point[0];
+        let dlat = self.lat - point[1];
+        dlon * dlon + dlat * dlat
+    }
+}
+
+fn build_camera_index(cams: &[Camera]) -> RTree<CameraPoint> {
+    RTree::bulk_load(
+        cams.iter()
+            .enumerate()
+            .map(|(index, cam)| {
+                let lat = cam.latitude.to_degrees();
+                CameraPoint { index, lat, proj_lon: cam.longitude.to_degrees() * lon_scale(lat) }
+            })
+            .collect(),
+    )
+}
+
+// projects a query point the same way camera positions are projected into
+// the index, so `locate_within_distance` compares like with like
+fn projected_query_point(lat: f64, lon: f64) -> [f64; 2] {
+    [lon * lon_scale(lat), lat]
+}
 
 #[derive(Debug)]
 struct SegmentResult {
@@ -24,10 +178,18 @@ struct SegmentResult {
     total_time: String,
     average_speed: f64,
     number_of_unique_cams: i32,
+    // naive sum of each camera's own exposure ("who saw you"); double-counts
+    // stretches of road that fall under more than one camera
     exposure_distance: f64,
     dist_percentage: f64,
     exposure_time: f64,
     time_percentage: f64,
+    // merged-interval union of camera coverage along the route ("how much of
+    // the trip was watched"); overlapping cameras only count once
+    exposure_distance_unique: f64,
+    dist_percentage_unique: f64,
+    exposure_time_unique: f64,
+    time_percentage_unique: f64,
     camera_dist_average: f64,
     camera_dist_median: f64,
     cameras: HashMap<usize, Camera>,
@@ -35,11 +197,21 @@ struct SegmentResult {
 
 #[derive(Debug, Deserialize, Clone)]
 struct Camera {
-    latitude: f64,
-    longitude: f64,
+    latitude: GeoCoord,
+    longitude: GeoCoord,
     camera_type: String,
     radius: f64,
     angle_of_view: i64,
+    // compass bearing the camera faces; missing for older CSVs without
+    // direction data, in which case `config.default_heading_deg` is used
+    #[serde(default)]
+    heading: Option<f64>,
+    // optional lens spec pair; when both are present they override
+    // `angle_of_view` via `2*atan(sensor_width / (2*focal_length))`
+    #[serde(default)]
+    focal_length_mm: Option<f64>,
+    #[serde(default)]
+    sensor_width_mm: Option<f64>,
     camera_model: String,
     url: String,
     camera_in_streetview: String,
@@ -59,7 +231,7 @@ fn default_float() -> f64{
 }
 
 
-fn haversine_distance(lat0: f64, lon0: f64, lat1: f64, lon1: f64) -> f64 {
+fn haversine_distance(lat0: f64, lon0: f64, lat1: f64, lon1: f64, earth_radius_km: f64) -> f64 {
 
     let lat0 = lat0.to_radians();
     let lat1 = lat1.to_radians();
@@ -72,20 +244,20 @@ fn haversine_distance(lat0: f64, lon0: f64, lat1: f64, lon1: f64) -> f64 {
     let central_angle = 2.0 * central_angle_inner.sqrt().asin();
 
     // return distance in meters
-    (R * central_angle * 1000.0).abs()
-   
+    (earth_radius_km * central_angle * 1000.0).abs()
+
 }
 
-fn get_coordinates(lat: f64, lon: f64, bearing: f64, distance: f64) -> (f64, f64) {
+fn get_coordinates(lat: f64, lon: f64, bearing: f64, distance: f64, earth_radius_km: f64) -> (f64, f64) {
     let brng = bearing.to_radians();
     let d = distance / 1000.0;
     let lat = lat.to_radians();
     let lon = lon.to_radians();
-    let lat2 = (lat.sin() * (d/R).cos() + lat.cos() * (d/R).sin() * brng.cos()).asin();
+    let lat2 = (lat.sin() * (d/earth_radius_km).cos() + lat.cos() * (d/earth_radius_km).sin() * brng.cos()).asin();
 
     // return new coordinates (tuple)
     (lat2.to_degrees(),
-     (lon + (brng.sin() * (d/R).sin() * lat.cos()).atan2((d/R).cos() - lat.sin() * lat2.sin())).to_degrees())
+     (lon + (brng.sin() * (d/earth_radius_km).sin() * lat.cos()).atan2((d/earth_radius_km).cos() - lat.sin() * lat2.sin())).to_degrees())
 }
 
 fn quick_distance(lat0: f64, lon0: f64, lat1: f64, lon1: f64) -> f64 {
@@ -96,6 +268,13 @@ fn quick_distance(lat0: f64, lon0: f64, lat1: f64, lon1: f64) -> f64 {
     (111319.0 * (x * x + y * y).sqrt()).abs()
 }
 
+fn compute_distance(lat0: f64, lon0: f64, lat1: f64, lon1: f64, config: &Config) -> f64 {
+    match config.distance_model {
+        DistanceModel::Quick => quick_distance(lat0, lon0, lat1, lon1),
+        DistanceModel::Haversine => haversine_distance(lat0, lon0, lat1, lon1, config.earth_radius_km),
+    }
+}
+
 fn get_bearing(lat0: f64, lon0: f64, lat1: f64, lon1: f64) -> f64 {
 
     let mut dlon = lon1.to_radians() - lon0.to_radians();
@@ -120,11 +299,46 @@ fn get_total_distance(segment: &TrackSegment) -> f64 {
         let (lon1, lat1) = segment.points[i -1].point().x_y();
         distance += quick_distance(lat0, lon0, lat1, lon1);
     }
-     
+
     // return distance in meters
     distance
 }
 
+// cumulative arc-length (in meters) of each track point from the start of the
+// segment; lets camera coverage be expressed as `[start, end]` intervals
+// along the route instead of per-point totals
+fn cumulative_distances(segment: &TrackSegment) -> Vec<f64> {
+    let mut cumulative: Vec<f64> = Vec::with_capacity(segment.points.len());
+    let mut acc: f64 = 0.0;
+    cumulative.push(0.0);
+    for (i, point) in segment.points.iter().enumerate().skip(1) {
+        let (lon0, lat0) = point.point().x_y();
+        let (lon1, lat1) = segment.points[i - 1].point().x_y();
+        acc += quick_distance(lat0, lon0, lat1, lon1);
+        cumulative.push(acc);
+    }
+    cumulative
+}
+
+// sort-and-sweep merge of possibly-overlapping `[start, end]` intervals
+fn merge_intervals(mut intervals: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    intervals.retain(|&(start, end)| end > start);
+    intervals.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    let mut merged: Vec<(f64, f64)> = Vec::new();
+    for next in intervals {
+        match merged.last_mut() {
+            Some(current) if next.0 <= current.1 => current.1 = current.1.max(next.1),
+            _ => merged.push(next),
+        }
+    }
+    merged
+}
+
+fn total_interval_length(intervals: &[(f64, f64)]) -> f64 {
+    intervals.iter().map(|(start, end)| end - start).sum()
+}
+
 fn avg_speed_per_point(dist: f64, time1: i64, time2: i64) -> f64 {
     dist / (time1 - time2).abs() as f64
 }
@@ -135,52 +349,100 @@ fn load_cameras(path: &str) -> Result<Vec<Camera>, Box<dyn Error>> {
 
     for result in reader.deserialize::<Camera>() {
         match result {
-            Ok(c) => cams.push(c),
+            Ok(c) => {
+                if !c.latitude.is_valid() || !c.longitude.is_valid() {
+                    eprintln!("Error with cameradata: invalid coordinates for camera {}", c.camera_model);
+                    continue;
+                }
+                cams.push(c);
+            },
             Err(e) => eprintln!("Error with cameradata: {}", e),
         };
     }
     Ok(cams)
 }
 
-fn dist_per_camera_attributes(dist: f64, cam: &Camera, lat: f64, lon: f64, addon: f64) -> bool {
-    if dist <= cam.radius + addon {
-        if cam.camera_type == "round" {
-            return true
-        } else if cam.camera_type == "directed" {
-            // angle to be changed when there is direction data
-            let angle = 180.0;  // cam.angle
+// horizontal FOV in degrees: derived from lens specs when available,
+// otherwise the CSV's `angle_of_view`
+fn camera_fov_degrees(cam: &Camera) -> f64 {
+    match (cam.focal_length_mm, cam.sensor_width_mm) {
+        (Some(focal), Some(sensor)) if focal > 0.0 => {
+            2.0 * (sensor / (2.0 * focal)).atan().to_degrees()
+        }
+        _ => cam.angle_of_view as f64,
+    }
+}
+
+// absolute angular distance between `heading` and `bearing`, wrapped into [0, 180]
+// so the comparison is correct across the 0/360° seam
+fn bearing_offset(heading: f64, bearing: f64) -> f64 {
+    (((bearing - heading + 540.0) % 360.0) - 180.0).abs()
+}
+
+// a directed camera sees sharpest along its heading and fades out toward the
+// edge of its FOV rather than cutting off sharply at `cam.radius`; points
+// outside the FOV entirely have zero effective radius
+fn effective_directed_radius(cam: &Camera, fov: f64, heading: f64, bearing: f64) -> f64 {
+    let half_fov = fov / 2.0;
+    if half_fov <= 0.0 { return 0.0; }
 
-            let half_fov = (cam.angle_of_view / 2) as f64;
-            let fov_range = (angle - half_fov, angle + half_fov);
+    let offset = bearing_offset(heading, bearing);
+    if offset >= half_fov { return 0.0; }
+
+    cam.radius * (offset / half_fov * (PI / 2.0)).cos()
+}
 
-            let bearing = get_bearing(cam.latitude,cam.longitude, lat, lon);
-            return fov_range.0 <= bearing && bearing <= fov_range.1
-        } else { return true }
+fn dist_per_camera_attributes(dist: f64, cam: &Camera, lat: f64, lon: f64, addon: f64, config: &Config) -> bool {
+    if cam.camera_type == "directed" {
+        let heading = cam.heading.unwrap_or(config.default_heading_deg);
+        let fov = camera_fov_degrees(cam);
+        let bearing = get_bearing(cam.latitude.to_degrees(), cam.longitude.to_degrees(), lat, lon);
+        dist <= effective_directed_radius(cam, fov, heading, bearing) + addon
+    } else {
+        dist <= cam.radius + addon
     }
-    return false
 }
 
-fn track_route(segment: &TrackSegment, cams: &[Camera]) -> (HashMap<usize, Vec<usize>>, HashSet<usize>, Vec<f64>) {
+fn track_route(
+    segment: &TrackSegment,
+    cams: &[Camera],
+    cam_index: &RTree<CameraPoint>,
+    collect_all_distances: bool,
+    config: &Config,
+) -> (HashMap<usize, Vec<usize>>, HashSet<usize>, Option<Vec<f64>>) {
     let mut cameras_per_point: HashMap<usize, Vec<usize>> = HashMap::new();
     let mut u_cams: Vec<usize> = Vec::new();
-    let mut all_distances: Vec<f64> = Vec::new();
+    // only needed for the mean/median distance statistics; sourced from the
+    // R-tree candidates below instead of a second O(points x cameras) pass
+    // over the whole camera list, which would defeat the point of the index
+    let mut all_distances: Option<Vec<f64>> = if collect_all_distances { Some(Vec::new()) } else { None };
+    let max_radius = cams.iter().map(|cam| cam.radius).fold(0.0, f64::max);
+    let search_radius_deg = (max_radius + config.accept_range_m) / METERS_PER_DEGREE;
 
     for (i, point) in segment.points.iter().enumerate() {
         // points_in_camera_fov
         let mut point_cams: Vec<usize> = Vec::new();
         let (lon, lat) = point.point().x_y();
-        for (index, cam) in cams.iter().enumerate() {
-            let distance = quick_distance(lat, lon, cam.latitude, cam.longitude);
-            all_distances.push(distance);
+
+        // only the cameras within max_radius + accept_range_m are candidates at all
+        let query_point = projected_query_point(lat, lon);
+        for candidate in cam_index.locate_within_distance(query_point, search_radius_deg * search_radius_deg) {
+            let index = candidate.index;
+            let cam = &cams[index];
+            let distance = compute_distance(lat, lon, cam.latitude.to_degrees(), cam.longitude.to_degrees(), config);
+
+            if let Some(distances) = all_distances.as_mut() {
+                distances.push(distance);
+            }
 
             // check distance against camera attributes
-            if dist_per_camera_attributes(distance, cam, lat, lon, 0.0) {
+            if dist_per_camera_attributes(distance, cam, lat, lon, 0.0, config) {
                 point_cams.push(index);
                 u_cams.push(index);
             }
         }
         if !point_cams.is_empty() { cameras_per_point.insert(i, point_cams); }
-        
+
     }
     u_cams.sort_unstable();
     let unique_cams: HashSet<_> = u_cams.drain(..).collect(); // dedup
@@ -204,12 +466,21 @@ fn calculate_mean(distances: &[f64]) -> f64 {
         sum as f64 / distances.len() as f64
 }
 
-fn test_points(mut lat: f64, mut lon: f64, cam: &Camera, course: &f64, points: &i32) -> i32 {
+#[allow(clippy::too_many_arguments)]
+fn test_points(mut lat: f64, mut lon: f64, cam: &Camera, cam_index: usize, rtree: &RTree<CameraPoint>, course: &f64, points: &i32, config: &Config) -> i32 {
     let mut result: i32 = 0;
+    let search_radius_deg = (cam.radius + config.accept_range_m) / METERS_PER_DEGREE;
     for _ in 1..=*points {
-        let (new_lat, new_lon) = get_coordinates(lat, lon, *course, RESOLUTION);
-        let cam_distance = quick_distance(new_lat, new_lon, cam.latitude, cam.longitude);
-        if dist_per_camera_attributes(cam_distance, cam, new_lat, new_lon, ACCEPTRANGE) {
+        let (new_lat, new_lon) = get_coordinates(lat, lon, *course, config.resolution_m, config.earth_radius_km);
+
+        // cheap R-tree guard before paying for the precise distance/angle check
+        let still_candidate = rtree
+            .locate_within_distance(projected_query_point(new_lat, new_lon), search_radius_deg * search_radius_deg)
+            .any(|candidate| candidate.index == cam_index);
+        if !still_candidate { break; }
+
+        let cam_distance = compute_distance(new_lat, new_lon, cam.latitude.to_degrees(), cam.longitude.to_degrees(), config);
+        if dist_per_camera_attributes(cam_distance, cam, new_lat, new_lon, config.accept_range_m, config) {
             result += 1;
         } else { break; }
         lat = new_lat;
@@ -218,8 +489,25 @@ fn test_points(mut lat: f64, mut lon: f64, cam: &Camera, course: &f64, points: &
     result
 }
 
-fn calculate_direction(cam_expo: &mut HashMap<usize, Camera>, cams_per_point: &HashMap<usize, Vec<usize>>, cams: &mut Vec<Camera>, backward: bool, segment: &TrackSegment) -> (f64, f64) {
-    let mut total_time: f64 = 0.0; let mut total_dist: f64 = 0.0; let mut lon1: f64 = 0.0; let mut lat1: f64 = 0.0; let mut time1: i64 = 0;
+// Walks the route in one direction, accumulating naive per-camera exposure
+// into `cam_expo` (the "who saw you" totals) and pushing each camera's
+// covered `[start, end]` arc-length/time span into `distance_intervals` /
+// `time_intervals` so the caller can sweep-merge them into the true unique
+// exposure afterwards (the "how much of the trip was watched" totals).
+#[allow(clippy::too_many_arguments)]
+fn calculate_direction(
+    cam_expo: &mut HashMap<usize, Camera>,
+    cams_per_point: &HashMap<usize, Vec<usize>>,
+    cams: &mut Vec<Camera>,
+    cam_index: &RTree<CameraPoint>,
+    backward: bool,
+    segment: &TrackSegment,
+    cumulative: &[f64],
+    distance_intervals: &mut Vec<(f64, f64)>,
+    time_intervals: &mut Vec<(f64, f64)>,
+    config: &Config,
+) {
+    let mut lon1: f64 = 0.0; let mut lat1: f64 = 0.0; let mut time1: i64 = 0;
     for (key, value) in cams_per_point.iter() {
         if backward && *key != 0 {
             (lon1, lat1) = segment.points[*key-1].point().x_y();
@@ -233,10 +521,9 @@ fn calculate_direction(cam_expo: &mut HashMap<usize, Camera>, cams_per_point: &H
         }
         let (lon0, lat0) = segment.points[*key].point().x_y();
         let time0 = segment.points[*key].time.unwrap().timestamp();
-        let mut highest_time = 0.0; let mut highest_dist = 0.0;
         let course = get_bearing(lat0, lon0, lat1, lon1);
-        let distance = quick_distance(lat0, lon0, lat1, lon1);
-        let points: i32 = if distance > RESOLUTION  { (distance / RESOLUTION).round() as i32 }  else { 1 };
+        let distance = compute_distance(lat0, lon0, lat1, lon1, config);
+        let points: i32 = if distance > config.resolution_m  { (distance / config.resolution_m).round() as i32 }  else { 1 };
 
         for cam in value {
             if !backward && cam_expo[&(*cam)].points.contains(&(&*key + 1)) { continue; }
@@ -244,20 +531,33 @@ fn calculate_direction(cam_expo: &mut HashMap<usize, Camera>, cams_per_point: &H
                 let pseudo_points: i32 = if backward && cams_per_point.contains_key(&(&*key - 1)) && cams_per_point[&(*key - 1)].contains(&*cam) {
                     points
                 } else {
-                    test_points(lat0, lon0, &cams[*cam], &course, &points)
+                    test_points(lat0, lon0, &cams[*cam], *cam, cam_index, &course, &points, config)
                 };
 
                 let avg = avg_speed_per_point(distance, time0, time1);
                 let cam_time: f64 = if avg != 0.0 {
-                    pseudo_points as f64 / (1.0 / RESOLUTION) / avg
+                    pseudo_points as f64 / (1.0 / config.resolution_m) / avg
                 } else {
                     (time1 - time0).abs() as f64
                 };
 
-                let cam_dist = pseudo_points as f64 / (1.0 / RESOLUTION);
+                let cam_dist = pseudo_points as f64 / (1.0 / config.resolution_m);
 
-                if cam_time > highest_time { highest_time = cam_time; }
-                if cam_dist > highest_dist { highest_dist = cam_dist; }
+                let point_cum_dist = cumulative[*key];
+                let (dist_start, dist_end) = if backward {
+                    ((point_cum_dist - cam_dist).max(0.0), point_cum_dist)
+                } else {
+                    (point_cum_dist, point_cum_dist + cam_dist)
+                };
+                distance_intervals.push((dist_start, dist_end));
+
+                let time0 = time0 as f64;
+                let (time_start, time_end) = if backward {
+                    (time0 - cam_time, time0)
+                } else {
+                    (time0, time0 + cam_time)
+                };
+                time_intervals.push((time_start, time_end));
 
                 if let Vacant(e) = cam_expo.entry(*cam) {
                     let mut cam_entry = &mut cams[*cam];
@@ -270,17 +570,187 @@ fn calculate_direction(cam_expo: &mut HashMap<usize, Camera>, cams_per_point: &H
                 }
             }
         }
+    }
+}
+
+fn route_feature(segment: &TrackSegment) -> Feature {
+    let coordinates: Vec<(f64, f64)> = segment.points.iter().map(|point| point.point().x_y()).collect();
 
-        total_dist += highest_dist;
-        total_time += highest_time;
+    let mut properties = JsonObject::new();
+    properties.insert("kind".to_string(), serde_json::json!("route"));
+
+    Feature {
+        bbox: None,
+        geometry: Some(Geometry::new_line_string(coordinates)),
+        id: None,
+        properties: Some(properties),
+        foreign_members: None,
+    }
+}
+
+// linear interpolation of the track's lon/lat at a given cumulative arc-length,
+// so exposure interval endpoints (sampled at `config.resolution_m`, not at
+// actual GPS fixes) land at a real position instead of snapping to whichever
+// track point happens to be nearest
+fn interpolate_point_at(segment: &TrackSegment, cumulative: &[f64], target: f64) -> (f64, f64) {
+    let last = cumulative.len() - 1;
+    if target <= cumulative[0] {
+        return segment.points[0].point().x_y();
+    }
+    if target >= cumulative[last] {
+        return segment.points[last].point().x_y();
+    }
+
+    let i1 = cumulative.partition_point(|&c| c < target);
+    let i0 = i1 - 1;
+    let (lon0, lat0) = segment.points[i0].point().x_y();
+    let (lon1, lat1) = segment.points[i1].point().x_y();
+    let span = cumulative[i1] - cumulative[i0];
+    let t = if span > 0.0 { (target - cumulative[i0]) / span } else { 0.0 };
+    (lon0 + (lon1 - lon0) * t, lat0 + (lat1 - lat0) * t)
+}
+
+// the timestamp (unix seconds) at a given cumulative arc-length, by the same
+// linear interpolation as `interpolate_point_at`
+fn interpolate_time_at(segment: &TrackSegment, cumulative: &[f64], target: f64) -> f64 {
+    let last = cumulative.len() - 1;
+    let time_at = |i: usize| segment.points[i].time.unwrap().timestamp() as f64;
+    if target <= cumulative[0] {
+        return time_at(0);
+    }
+    if target >= cumulative[last] {
+        return time_at(last);
+    }
+
+    let i1 = cumulative.partition_point(|&c| c < target);
+    let i0 = i1 - 1;
+    let span = cumulative[i1] - cumulative[i0];
+    let t = if span > 0.0 { (target - cumulative[i0]) / span } else { 0.0 };
+    time_at(i0) + (time_at(i1) - time_at(i0)) * t
+}
+
+// one LineString per merged exposure interval, coloured for easy rendering;
+// endpoints are interpolated along the route so the line always has at least
+// two positions, even when no GPS fix falls inside the interval. Each
+// interval's exposure time is derived directly from the route's timestamps
+// at its own start/end, rather than read off a separately time-merged
+// interval list by index -- merging on the distance axis and the time axis
+// can legitimately produce different interval counts (e.g. idling at a
+// light closes a time gap that the matching distance gap doesn't), so there
+// is no index correspondence between the two to rely on
+fn exposed_segment_features(
+    segment: &TrackSegment,
+    cumulative: &[f64],
+    merged_distance: &[(f64, f64)],
+) -> Vec<Feature> {
+    merged_distance.iter().map(|&(start, end)| {
+        let mut coordinates: Vec<(f64, f64)> = vec![interpolate_point_at(segment, cumulative, start)];
+        coordinates.extend(
+            segment.points.iter().enumerate()
+                .filter(|(i, _)| cumulative[*i] > start && cumulative[*i] < end)
+                .map(|(_, point)| point.point().x_y())
+        );
+        coordinates.push(interpolate_point_at(segment, cumulative, end));
+
+        let exposure_time_s = interpolate_time_at(segment, cumulative, end) - interpolate_time_at(segment, cumulative, start);
+
+        let mut properties = JsonObject::new();
+        properties.insert("kind".to_string(), serde_json::json!("exposed_segment"));
+        properties.insert("exposure_distance_m".to_string(), serde_json::json!(end - start));
+        properties.insert("exposure_time_s".to_string(), serde_json::json!(exposure_time_s));
+        properties.insert("stroke".to_string(), serde_json::json!("#ff0000"));
+        properties.insert("stroke-width".to_string(), serde_json::json!(4));
+
+        Feature {
+            bbox: None,
+            geometry: Some(Geometry::new_line_string(coordinates)),
+            id: None,
+            properties: Some(properties),
+            foreign_members: None,
+        }
+    }).collect()
+}
+
+// approximates a camera's coverage footprint: a full circle for "round"
+// cameras, a pie slice (apex at the camera) spanning its FOV for "directed"
+// ones
+fn camera_footprint_ring(cam: &Camera, config: &Config) -> Vec<(f64, f64)> {
+    const SEGMENTS: usize = 32;
+
+    if cam.camera_type == "directed" {
+        let heading = cam.heading.unwrap_or(config.default_heading_deg);
+        let fov = camera_fov_degrees(cam);
+        let start = heading - fov / 2.0;
+        let end = heading + fov / 2.0;
+
+        let mut ring = vec![(cam.longitude.to_degrees(), cam.latitude.to_degrees())];
+        for i in 0..=SEGMENTS {
+            let bearing = start + (end - start) * (i as f64 / SEGMENTS as f64);
+            let (lat, lon) = get_coordinates(cam.latitude.to_degrees(), cam.longitude.to_degrees(), bearing, cam.radius, config.earth_radius_km);
+            ring.push((lon, lat));
+        }
+        ring.push((cam.longitude.to_degrees(), cam.latitude.to_degrees()));
+        ring
+    } else {
+        (0..=SEGMENTS).map(|i| {
+            let bearing = 360.0 * (i as f64 / SEGMENTS as f64);
+            let (lat, lon) = get_coordinates(cam.latitude.to_degrees(), cam.longitude.to_degrees(), bearing, cam.radius, config.earth_radius_km);
+            (lon, lat)
+        }).collect()
     }
-    (total_dist, total_time)
+}
+
+fn camera_features(cam_expo: &HashMap<usize, Camera>, config: &Config) -> Vec<Feature> {
+    cam_expo.values().map(|cam| {
+        let ring = camera_footprint_ring(cam, config);
+
+        let mut properties = JsonObject::new();
+        properties.insert("kind".to_string(), serde_json::json!("camera"));
+        properties.insert("camera_type".to_string(), serde_json::json!(cam.camera_type));
+        properties.insert("camera_model".to_string(), serde_json::json!(cam.camera_model));
+        properties.insert("url".to_string(), serde_json::json!(cam.url));
+        properties.insert("dist".to_string(), serde_json::json!(cam.dist));
+        properties.insert("time".to_string(), serde_json::json!(cam.time));
+        properties.insert("fill".to_string(), serde_json::json!("#3388ff"));
+        properties.insert("fill-opacity".to_string(), serde_json::json!(0.3));
+
+        Feature {
+            bbox: None,
+            geometry: Some(Geometry::new_polygon(vec![ring])),
+            id: None,
+            properties: Some(properties),
+            foreign_members: None,
+        }
+    }).collect()
+}
+
+fn build_geojson(
+    segment: &TrackSegment,
+    cumulative: &[f64],
+    merged_distance: &[(f64, f64)],
+    cam_expo: &HashMap<usize, Camera>,
+    config: &Config,
+) -> FeatureCollection {
+    let mut features = vec![route_feature(segment)];
+    features.extend(exposed_segment_features(segment, cumulative, merged_distance));
+    features.extend(camera_features(cam_expo, config));
+    FeatureCollection { bbox: None, features, foreign_members: None }
 }
 
 fn main() {
 
-    let path = env::args().nth(1).expect("No .gpx file path."); 
-    let cam_path = env::args().nth(2).expect("No camerafile path."); 
+    let geojson_mode = env::args().any(|arg| arg == "--geojson");
+    let positional: Vec<String> = env::args().skip(1).filter(|arg| arg != "--geojson").collect();
+
+    let path = positional.first().expect("No .gpx file path.").to_owned();
+    let cam_path = positional.get(1).expect("No camerafile path.").to_owned();
+    let config = match positional.get(2) {
+        Some(config_path) => match load_config(config_path) {
+            Ok(config) => config,
+            Err(error) => panic!("Error with config file: {:?}", error),
+        },
+        None => Config::default(),
+    };
     let mut cams = match load_cameras(&cam_path) {
         Ok(cam) => cam,
         Err(error) => {
@@ -300,24 +770,45 @@ fn main() {
     };
 
 
+    let cam_index = build_camera_index(&cams);
+
     // iterate over track and segments
     for (t, track) in gpx.tracks.iter().enumerate() {
         for (s, segment) in track.segments.iter().enumerate() {
-            let (cameras_per_point, unique_cams, mut distances) = track_route(segment, &cams);
+            let (cameras_per_point, unique_cams, distances) = track_route(segment, &cams, &cam_index, true, &config);
             // sort distances for median calculation
+            let mut distances = distances.expect("distance statistics were requested");
             distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
 
             let mut cam_expo: HashMap<usize, Camera> = HashMap::new();
-            let (b_dist, b_time) = calculate_direction(&mut cam_expo, &cameras_per_point, &mut cams, true, segment);
-            let (f_dist, f_time) = calculate_direction(&mut cam_expo, &cameras_per_point, &mut cams, false, segment);
+            let cumulative = cumulative_distances(segment);
+            let mut distance_intervals: Vec<(f64, f64)> = Vec::new();
+            let mut time_intervals: Vec<(f64, f64)> = Vec::new();
+            calculate_direction(&mut cam_expo, &cameras_per_point, &mut cams, &cam_index, true, segment, &cumulative, &mut distance_intervals, &mut time_intervals, &config);
+            calculate_direction(&mut cam_expo, &cameras_per_point, &mut cams, &cam_index, false, segment, &cumulative, &mut distance_intervals, &mut time_intervals, &config);
 
             let total_distance = get_total_distance(segment);
             let total_time = segment.points[segment.points.len() - 1].time.unwrap() - segment.points[0].time.unwrap();
-            let dist = b_dist + f_dist; let time = b_time + f_time;
+
+            // naive: sum each camera's own exposure independently
+            let dist: f64 = cam_expo.values().map(|cam| cam.dist).sum();
+            let time: f64 = cam_expo.values().map(|cam| cam.time).sum();
             let dist_percentage = dist/total_distance * 100.0;
             let time_percentage = time / total_time.num_seconds() as f64 * 100.0;
-            // let dist_neat = format!("{}% {}/{}", dist/total_distance * 100.0, dist, total_distance);
-            // let time_neat = format!("{}% {}/{:?}", time / total_time_secs as f64 * 100.0, time, total_time);
+
+            // unique: merge overlapping camera coverage before summing
+            let merged_distance = merge_intervals(distance_intervals);
+            let merged_time = merge_intervals(time_intervals);
+            let dist_unique = total_interval_length(&merged_distance);
+            let time_unique = total_interval_length(&merged_time);
+            let dist_percentage_unique = dist_unique/total_distance * 100.0;
+            let time_percentage_unique = time_unique / total_time.num_seconds() as f64 * 100.0;
+
+            if geojson_mode {
+                let feature_collection = build_geojson(segment, &cumulative, &merged_distance, &cam_expo, &config);
+                println!("{}", serde_json::to_string_pretty(&feature_collection).unwrap());
+                continue;
+            }
 
             let (_, name) = &path.rsplit_once('/').unwrap();
             let result = SegmentResult {
@@ -327,6 +818,8 @@ fn main() {
                 number_of_unique_cams: unique_cams.len() as i32,
                 exposure_distance: dist, dist_percentage,
                 exposure_time: time, time_percentage,
+                exposure_distance_unique: dist_unique, dist_percentage_unique,
+                exposure_time_unique: time_unique, time_percentage_unique,
                 camera_dist_average: calculate_mean(&distances), camera_dist_median: calculate_median(&distances),
                 cameras: cam_expo
             };
@@ -335,3 +828,81 @@ fn main() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_camera() -> Camera {
+        Camera {
+            latitude: GeoCoord::from_degrees(0.0),
+            longitude: GeoCoord::from_degrees(0.0),
+            camera_type: "directed".to_string(),
+            radius: 50.0,
+            angle_of_view: 60,
+            heading: Some(0.0),
+            focal_length_mm: None,
+            sensor_width_mm: None,
+            camera_model: "test".to_string(),
+            url: String::new(),
+            camera_in_streetview: String::new(),
+            points: default_set(),
+            dist: 0.0,
+            time: 0.0,
+        }
+    }
+
+    #[test]
+    fn merge_intervals_joins_overlapping_and_touching_spans() {
+        let merged = merge_intervals(vec![(0.0, 5.0), (4.0, 10.0), (20.0, 25.0), (25.0, 30.0)]);
+        assert_eq!(merged, vec![(0.0, 10.0), (20.0, 30.0)]);
+    }
+
+    #[test]
+    fn merge_intervals_keeps_disjoint_spans_separate() {
+        let merged = merge_intervals(vec![(0.0, 1.0), (5.0, 6.0)]);
+        assert_eq!(merged, vec![(0.0, 1.0), (5.0, 6.0)]);
+    }
+
+    #[test]
+    fn merge_intervals_drops_zero_and_negative_length_spans() {
+        let merged = merge_intervals(vec![(3.0, 3.0), (1.0, 0.0), (0.0, 2.0)]);
+        assert_eq!(merged, vec![(0.0, 2.0)]);
+    }
+
+    #[test]
+    fn geocoord_round_trips_through_fixed_point() {
+        let coord = GeoCoord::from_degrees(51.5074);
+        assert!(coord.is_valid());
+        assert!((coord.to_degrees() - 51.5074).abs() < 1e-6);
+    }
+
+    #[test]
+    fn geocoord_flags_out_of_range_values_as_invalid() {
+        assert!(!GeoCoord::from_degrees(f64::NAN).is_valid());
+        assert!(!GeoCoord::from_degrees(1000.0).is_valid());
+        assert!(!GeoCoord::default().is_valid());
+    }
+
+    #[test]
+    fn bearing_offset_wraps_across_the_0_360_seam() {
+        assert_eq!(bearing_offset(350.0, 10.0), 20.0);
+        assert_eq!(bearing_offset(10.0, 350.0), 20.0);
+        assert_eq!(bearing_offset(180.0, 180.0), 0.0);
+    }
+
+    #[test]
+    fn effective_directed_radius_is_zero_outside_the_fov() {
+        let cam = test_camera();
+        assert_eq!(effective_directed_radius(&cam, 60.0, 0.0, 180.0), 0.0);
+    }
+
+    #[test]
+    fn effective_directed_radius_tapers_toward_the_fov_edge() {
+        let cam = test_camera();
+        let center = effective_directed_radius(&cam, 60.0, 0.0, 0.0);
+        let edge = effective_directed_radius(&cam, 60.0, 0.0, 29.0);
+        assert_eq!(center, cam.radius);
+        assert!(edge > 0.0 && edge < center);
+    }
+}